@@ -0,0 +1,346 @@
+//! Anti-entropy state synchronization for replicated processes.
+//!
+//! A process that keeps its state via [`crate::get_typed_state`]/`set_state`
+//! has no built-in way to reconcile divergent copies held by other
+//! instances of itself across nodes. This module implements a gossip-style
+//! reconciliation round: each participant remembers the latest
+//! [`Versioned`] value it has seen written by every participant (including
+//! itself), keyed by writer `Address`, plus the version vector that's just
+//! each entry's timestamp. Periodically (driven by [`crate::timer`]) a
+//! participant sends a peer a [`SyncDigest`] of its version vector, and the
+//! peer replies with a [`SyncDelta`] of every writer's entry the requester
+//! is behind on -- not just the peer's own writes, so an update can hop
+//! through an intermediary to reach a third participant. A full round is
+//! idempotent and monotonic: re-running it without new local writes
+//! produces no further changes, so a set of peers eventually converges
+//! without a central coordinator.
+//!
+//! A process registers its state once with [`register_syncable`], then
+//! drives rounds with just a peer address via [`run_sync_round`] -- handy
+//! for calling from a [`crate::timer`]-fired loop without threading the
+//! state through every call site.
+
+use crate::Address;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A logical clock per participant, used to tell which of two copies of a
+/// key is newer without relying on wall-clock time.
+pub type VersionVector = HashMap<Address, u64>;
+
+/// A value an app can combine two *concurrent* writes of into one. Per
+/// writer, updates are strictly ordered by timestamp and newer simply
+/// replaces older (see [`apply_delta`]) -- `merge` is only ever invoked to
+/// combine the latest values of two *different* writers when materializing
+/// the application-visible view with [`Syncable::value`], so it must be
+/// commutative and associative across the whole participant set (e.g. set
+/// union, or any other CRDT merge) rather than relying on the order
+/// participants happen to be folded in.
+pub trait Mergeable: Serialize + for<'de> Deserialize<'de> + Clone {
+    /// Merge `other` (written by some other participant at
+    /// `other_timestamp`) into `self` (written by a different participant
+    /// at `own_timestamp`).
+    fn merge(&mut self, other: &Self, other_timestamp: u64, own_timestamp: u64);
+}
+
+/// One writer's value plus the bookkeeping needed to reconcile it: who
+/// wrote it, and when.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub value: T,
+    pub written_by: Address,
+    pub timestamp: u64,
+}
+
+/// Persisted state for a syncable value: the latest value seen from each
+/// participant, keyed by writer.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Syncable<T> {
+    entries: HashMap<Address, Versioned<T>>,
+}
+
+impl<T: Mergeable> Syncable<T> {
+    /// Wrap a freshly-initialized value as owned by `us` at `timestamp`.
+    pub fn new(value: T, us: Address, timestamp: u64) -> Self {
+        let mut entries = HashMap::new();
+        entries.insert(
+            us.clone(),
+            Versioned {
+                value,
+                written_by: us,
+                timestamp,
+            },
+        );
+        Self { entries }
+    }
+
+    /// Record a local write, replacing our own entry outright (a
+    /// participant's own writes are strictly ordered, so there's nothing to
+    /// merge here).
+    pub fn write(&mut self, value: T, us: Address, timestamp: u64) {
+        self.entries.insert(
+            us.clone(),
+            Versioned {
+                value,
+                written_by: us,
+                timestamp,
+            },
+        );
+    }
+
+    /// The version vector to advertise in a [`SyncDigest`]: each known
+    /// writer's latest timestamp.
+    pub fn version_vector(&self) -> VersionVector {
+        self.entries
+            .iter()
+            .map(|(addr, v)| (addr.clone(), v.timestamp))
+            .collect()
+    }
+
+    /// The application-visible value: every participant's latest known
+    /// write, folded together with [`Mergeable::merge`]. Concurrent writes
+    /// from different participants are combined rather than one clobbering
+    /// the other, so `merge` must be commutative/associative for this to be
+    /// well-defined regardless of fold order.
+    pub fn value(&self) -> T {
+        let mut iter = self.entries.values();
+        let first = iter
+            .next()
+            .expect("a Syncable always has at least its own entry");
+        let mut acc = first.value.clone();
+        let mut acc_timestamp = first.timestamp;
+        for v in iter {
+            acc.merge(&v.value, v.timestamp, acc_timestamp);
+            acc_timestamp = acc_timestamp.max(v.timestamp);
+        }
+        acc
+    }
+
+    /// Load a [`Syncable<T>`] from persisted process state, if any.
+    pub fn load() -> Option<Self>
+    where
+        T: 'static,
+    {
+        crate::get_typed_state(|bytes| Ok(serde_json::from_slice(bytes)?))
+    }
+
+    /// Persist this [`Syncable<T>`] as the process's state.
+    pub fn save(&self) -> anyhow::Result<()> {
+        crate::set_state(&serde_json::to_vec(self)?);
+        Ok(())
+    }
+}
+
+/// Sent to a peer to ask which updates it has that we don't.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SyncDigest {
+    pub version_vector: VersionVector,
+}
+
+/// A peer's reply to a [`SyncDigest`]: every writer's entry the requester is
+/// behind on, whether or not that writer is the peer itself.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SyncDelta<T> {
+    pub updates: Vec<Versioned<T>>,
+}
+
+/// Compare `ours` against `their_digest` and return every writer's entry
+/// the requester is behind on -- including writers other than us, so a
+/// third participant's update can relay through an intermediary instead of
+/// only ever being shared by the writer itself.
+pub fn diff_against<T: Clone>(ours: &Syncable<T>, their_digest: &SyncDigest) -> Vec<Versioned<T>> {
+    ours.entries
+        .values()
+        .filter(|v| {
+            their_digest
+                .version_vector
+                .get(&v.written_by)
+                .copied()
+                .unwrap_or(0)
+                < v.timestamp
+        })
+        .cloned()
+        .collect()
+}
+
+/// Apply a [`SyncDelta`] received from a peer: for each update, if it's
+/// newer than what we have recorded for that writer, replace our entry for
+/// that writer outright. Per writer, updates are strictly ordered by
+/// timestamp, so this is always a replace, never a [`Mergeable::merge`] --
+/// merging only happens across *different* writers' entries, in
+/// [`Syncable::value`].
+pub fn apply_delta<T: Mergeable>(syncable: &mut Syncable<T>, delta: SyncDelta<T>) {
+    for update in delta.updates {
+        let our_latest = syncable
+            .entries
+            .get(&update.written_by)
+            .map(|v| v.timestamp)
+            .unwrap_or(0);
+        if update.timestamp > our_latest {
+            syncable.entries.insert(update.written_by.clone(), update);
+        }
+    }
+}
+
+/// Run one gossip round against `peer` over an already-held [`Syncable<T>`]:
+/// send it our [`SyncDigest`], correlate its reply as the [`SyncDelta`] (not
+/// just whatever message arrives next), and apply it. Returns `true` if
+/// anything changed (useful for deciding whether to re-persist immediately
+/// rather than waiting for the next scheduled write).
+fn run_sync_round_on<T: Mergeable>(syncable: &mut Syncable<T>, peer: &Address) -> anyhow::Result<bool> {
+    let message = crate::Request::new()
+        .target(peer.clone())
+        .body(serde_json::to_vec(&SyncDigest {
+            version_vector: syncable.version_vector(),
+        })?)
+        .send_and_await_response(5)??;
+    let delta: SyncDelta<T> = serde_json::from_slice(message.body())?;
+    let before = syncable.version_vector();
+    apply_delta(syncable, delta);
+    Ok(syncable.version_vector() != before)
+}
+
+/// Reply to an incoming [`SyncDigest`] with the [`SyncDelta`] of writer
+/// entries the requester is behind on.
+pub fn respond_to_digest<T: Mergeable>(syncable: &Syncable<T>, digest: &SyncDigest) -> anyhow::Result<()> {
+    let updates = diff_against(syncable, digest);
+    crate::Response::new()
+        .body(serde_json::to_vec(&SyncDelta { updates })?)
+        .send()?;
+    Ok(())
+}
+
+thread_local! {
+    /// The syncable this process registered with [`register_syncable`], so
+    /// that [`run_sync_round`] can be driven by just a peer address (e.g.
+    /// from a `timer`-fired gossip round) without the caller threading the
+    /// state through every call site.
+    static REGISTERED_SYNCABLE: RefCell<Option<Box<dyn Any>>> = RefCell::new(None);
+}
+
+/// Register the state this process keeps as the syncable that
+/// [`run_sync_round`] reconciles. Only one syncable may be registered at a
+/// time; call this once (e.g. from `init`, after [`Syncable::load`] or
+/// [`Syncable::new`]) before the first scheduled round.
+pub fn register_syncable<T: Mergeable + 'static>(state: Syncable<T>) {
+    REGISTERED_SYNCABLE.with(|slot| *slot.borrow_mut() = Some(Box::new(state)));
+}
+
+/// Run one gossip round against `peer` using the syncable registered with
+/// [`register_syncable`]. Returns `true` if anything changed. The registered
+/// syncable stays inside this module: this call does not persist it, and no
+/// function here hands it back for [`Syncable::save`].
+pub fn run_sync_round<T: Mergeable + 'static>(peer: &Address) -> anyhow::Result<bool> {
+    REGISTERED_SYNCABLE.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        let boxed = slot
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("run_sync_round: no syncable registered; call register_syncable first"))?;
+        let syncable = boxed
+            .downcast_mut::<Syncable<T>>()
+            .ok_or_else(|| anyhow::anyhow!("run_sync_round: registered syncable is not a Syncable<T> for this T"))?;
+        run_sync_round_on(syncable, peer)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn addr(name: &str) -> Address {
+        Address::new("our", crate::ProcessId::new(Some(name), "distro", "sys"))
+    }
+
+    #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+    struct TagSet(HashSet<String>);
+
+    impl Mergeable for TagSet {
+        fn merge(&mut self, other: &Self, _other_timestamp: u64, _own_timestamp: u64) {
+            self.0.extend(other.0.iter().cloned());
+        }
+    }
+
+    fn one_tag(tag: &str) -> TagSet {
+        TagSet(HashSet::from([tag.to_string()]))
+    }
+
+    /// A full round (digest -> delta -> apply) between two participants
+    /// should converge them, and re-running it afterwards without any new
+    /// writes should be a no-op.
+    #[test]
+    fn sync_round_is_idempotent_once_converged() {
+        let node_a = addr("a");
+        let node_b = addr("b");
+
+        let mut syncable_a = Syncable::new(one_tag("from-a"), node_a.clone(), 1);
+        let syncable_b = Syncable::new(one_tag("from-b"), node_b.clone(), 1);
+
+        // a asks b for anything it's behind on.
+        let digest_from_a = SyncDigest {
+            version_vector: syncable_a.version_vector(),
+        };
+        let delta_for_a = diff_against(&syncable_b, &digest_from_a);
+        assert_eq!(delta_for_a.len(), 1);
+        assert_eq!(delta_for_a[0].written_by, node_b);
+
+        apply_delta(&mut syncable_a, SyncDelta { updates: delta_for_a });
+        assert_eq!(syncable_a.value().0, HashSet::from(["from-a".to_string(), "from-b".to_string()]));
+
+        // Running the same round again with a's now-updated digest should
+        // find nothing new to send: b's entry is already known to a.
+        let digest_from_a_again = SyncDigest {
+            version_vector: syncable_a.version_vector(),
+        };
+        let delta_again = diff_against(&syncable_b, &digest_from_a_again);
+        assert!(delta_again.is_empty());
+
+        let before = syncable_a.version_vector();
+        apply_delta(&mut syncable_a, SyncDelta { updates: delta_again });
+        assert_eq!(syncable_a.version_vector(), before);
+    }
+
+    /// An update from a third participant, relayed through an intermediary
+    /// that has already learned it, must still reach a node that only
+    /// talks to the intermediary.
+    #[test]
+    fn third_party_updates_relay_through_an_intermediary() {
+        let node_a = addr("a");
+        let node_b = addr("b");
+        let node_c = addr("c");
+
+        let syncable_c = Syncable::new(one_tag("from-c"), node_c.clone(), 1);
+        // b has already learned about c's update in an earlier round.
+        let mut syncable_b = Syncable::new(one_tag("from-b"), node_b.clone(), 1);
+        apply_delta(
+            &mut syncable_b,
+            SyncDelta {
+                updates: diff_against(
+                    &syncable_c,
+                    &SyncDigest {
+                        version_vector: syncable_b.version_vector(),
+                    },
+                ),
+            },
+        );
+        assert!(syncable_b.value().0.contains("from-c"));
+
+        // a, which has never talked to c, syncs against b and should still
+        // pick up c's update via b's relayed entry.
+        let mut syncable_a = Syncable::new(one_tag("from-a"), node_a.clone(), 1);
+        let delta_for_a = diff_against(
+            &syncable_b,
+            &SyncDigest {
+                version_vector: syncable_a.version_vector(),
+            },
+        );
+        apply_delta(&mut syncable_a, SyncDelta { updates: delta_for_a });
+
+        assert_eq!(
+            syncable_a.value().0,
+            HashSet::from(["from-a".to_string(), "from-b".to_string(), "from-c".to_string()])
+        );
+    }
+}
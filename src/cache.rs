@@ -0,0 +1,181 @@
+//! A TTL cache layered over the [`crate::kv`] module.
+//!
+//! The kv backend has no native notion of expiry, so a [`Cache`] serializes
+//! the expiry timestamp alongside the payload and checks it on every read,
+//! lazily deleting anything it finds expired. It also has no native prefix
+//! scan, so `Cache` keeps its own index of the keys it has written
+//! (persisted in the same kv database) so that pattern-based invalidation
+//! and the periodic sweep can operate without the caller having to track
+//! keys itself. This gives processes a memoization/caching primitive
+//! without each app reinventing expiry bookkeeping.
+
+use crate::kv::Kv;
+use crate::timer;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Reserved kv key under which the set of keys this `Cache` has written is
+/// persisted, so the index survives across restarts of the owning process.
+const INDEX_KEY: &[u8] = b"__cache_index__";
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Unix millis from which on this entry is considered absent, or `None`
+    /// if it never expires.
+    expires_at: Option<u64>,
+    bytes: Vec<u8>,
+}
+
+impl Entry {
+    fn new<T: Serialize>(value: &T, ttl: Option<Duration>, now_millis: u64) -> anyhow::Result<Self> {
+        Ok(Self {
+            expires_at: ttl.map(|ttl| now_millis + ttl.as_millis() as u64),
+            bytes: serde_json::to_vec(value)?,
+        })
+    }
+
+    fn is_expired(&self, now_millis: u64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if now_millis >= expires_at)
+    }
+}
+
+/// A TTL-aware cache backed by a named kv database.
+pub struct Cache {
+    kv: Kv,
+    index: RefCell<HashSet<String>>,
+}
+
+/// Which keys a [`Cache::invalidate`] call should remove.
+pub enum InvalidatePattern {
+    /// Every key in the cache.
+    All,
+    /// Every key starting with this prefix.
+    Prefix(String),
+    /// Exactly these keys.
+    Keys(Vec<String>),
+}
+
+impl Cache {
+    /// Open (creating if necessary) the named cache, loading its key index
+    /// back from kv.
+    pub fn open(db: &str) -> anyhow::Result<Self> {
+        let kv = crate::kv::open(db)?;
+        let index = match kv.get(INDEX_KEY)? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => HashSet::new(),
+        };
+        Ok(Self {
+            kv,
+            index: RefCell::new(index),
+        })
+    }
+
+    fn persist_index(&self) -> anyhow::Result<()> {
+        self.kv
+            .set(INDEX_KEY, serde_json::to_vec(&*self.index.borrow())?)
+    }
+
+    /// Store `value` at `key`, expiring after `ttl` (or never, if `None`).
+    /// `now_millis` is the current time in Unix millis; this module does
+    /// not read the clock itself so that it stays deterministic in tests.
+    pub fn set<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Option<Duration>,
+        now_millis: u64,
+    ) -> anyhow::Result<()> {
+        let entry = Entry::new(value, ttl, now_millis)?;
+        self.kv.set(key.as_bytes(), serde_json::to_vec(&entry)?)?;
+        self.index.borrow_mut().insert(key.to_string());
+        self.persist_index()
+    }
+
+    /// Fetch and deserialize the value at `key`, treating an expired entry
+    /// as absent and lazily deleting it. `now_millis` is the current time
+    /// in Unix millis.
+    pub fn get_typed<T: DeserializeOwned>(
+        &self,
+        key: &str,
+        now_millis: u64,
+    ) -> anyhow::Result<Option<T>> {
+        let Some(raw) = self.kv.get(key.as_bytes())? else {
+            return Ok(None);
+        };
+        let entry: Entry = serde_json::from_slice(&raw)?;
+        if entry.is_expired(now_millis) {
+            self.kv.delete(key.as_bytes())?;
+            if self.index.borrow_mut().remove(key) {
+                self.persist_index()?;
+            }
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&entry.bytes)?))
+    }
+
+    /// Remove the keys matching `pattern`, using the cache's own key index
+    /// to resolve `All`/`Prefix`.
+    pub fn invalidate(&self, pattern: InvalidatePattern) -> anyhow::Result<()> {
+        let to_delete: Vec<String> = match &pattern {
+            InvalidatePattern::All => self.index.borrow().iter().cloned().collect(),
+            InvalidatePattern::Prefix(prefix) => self
+                .index
+                .borrow()
+                .iter()
+                .filter(|k| k.starts_with(prefix.as_str()))
+                .cloned()
+                .collect(),
+            InvalidatePattern::Keys(keys) => keys.clone(),
+        };
+        let mut index = self.index.borrow_mut();
+        for key in &to_delete {
+            self.kv.delete(key.as_bytes())?;
+            index.remove(key);
+        }
+        drop(index);
+        self.persist_index()
+    }
+
+    /// Schedule the next sweep: after `interval`, a one-shot timer fires that
+    /// a process can use to call [`Cache::sweep`] and delete expired entries
+    /// it encounters, even if nothing ever reads them. For periodic sweeps,
+    /// call this first from `init` and again each time the timer fires.
+    pub fn schedule_sweep(interval: Duration) -> anyhow::Result<()> {
+        timer::set_timer(interval, Some(b"cache_sweep".to_vec()))
+    }
+
+    /// Delete any indexed key that is present but expired. Returns the keys
+    /// that were swept.
+    pub fn sweep(&self, now_millis: u64) -> anyhow::Result<Vec<String>> {
+        let keys: Vec<String> = self.index.borrow().iter().cloned().collect();
+        let mut swept = Vec::new();
+        for key in keys {
+            let was_present = self.kv.get(key.as_bytes())?.is_some();
+            if was_present && self.get_typed::<serde_json::Value>(&key, now_millis)?.is_none() {
+                swept.push(key);
+            }
+        }
+        Ok(swept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_without_ttl_never_expires() {
+        let entry = Entry::new(&"value", None, 1_000).unwrap();
+        assert!(!entry.is_expired(u64::MAX));
+    }
+
+    #[test]
+    fn entry_expires_once_ttl_elapses() {
+        let entry = Entry::new(&"value", Some(Duration::from_millis(500)), 1_000).unwrap();
+        assert!(!entry.is_expired(1_499));
+        assert!(entry.is_expired(1_500));
+        assert!(entry.is_expired(2_000));
+    }
+}
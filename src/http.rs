@@ -0,0 +1,284 @@
+//! Interact with the HTTP server and client modules.
+//! Contains types from the `http` crate to use as well.
+
+use crate::{Address, ProcessId, Request};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn http_client_address() -> Address {
+    Address::new("our", ProcessId::new(Some("http_client"), "distro", "sys"))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct OutgoingHttpRequest {
+    method: String,
+    url: String,
+    headers: HashMap<String, String>,
+    timeout_millis: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct IncomingHttpResponse {
+    status: u16,
+    headers: HashMap<String, String>,
+    /// The canonical URL/id the server reports for this resource, once
+    /// redirects have been followed; used by [`fetch_with`]'s
+    /// `expected_url` check.
+    canonical_url: Option<String>,
+}
+
+/// A plain, guardrail-free fetch: send `method` to `url` with `headers` and
+/// `body`, and return the raw response. [`fetch_with`] wraps this with
+/// retry, size caps, and redirect-depth limits for untrusted or flaky
+/// remotes.
+pub fn send_request(
+    method: http::Method,
+    url: &str,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+    timeout: Duration,
+) -> anyhow::Result<http::Response<Vec<u8>>> {
+    Request::new()
+        .target(http_client_address())
+        .body(serde_json::to_vec(&OutgoingHttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers,
+            timeout_millis: timeout.as_millis() as u64,
+        })?)
+        .blob(crate::LazyLoadBlob { mime: None, bytes: body })
+        .send()?;
+    let message = crate::await_message()?;
+    let incoming: IncomingHttpResponse = serde_json::from_slice(message.body())?;
+    let mut builder = http::Response::builder()
+        .status(http::StatusCode::from_u16(incoming.status)?);
+    for (key, value) in &incoming.headers {
+        builder = builder.header(key, value);
+    }
+    if let Some(canonical) = &incoming.canonical_url {
+        builder = builder.header("x-canonical-url", canonical);
+    }
+    let bytes = crate::get_blob().map(|b| b.bytes).unwrap_or_default();
+    Ok(builder.body(bytes)?)
+}
+
+/// Why a [`fetch_with`] call gave up.
+#[derive(Debug)]
+pub enum FetchError {
+    /// The response body exceeded `max_body_bytes` (checked once the full
+    /// body has been received).
+    TooLarge,
+    /// Following redirects exceeded `max_redirects`.
+    TooManyRedirects,
+    /// An attempt didn't complete within its per-attempt `timeout`.
+    Timeout,
+    /// All `max_attempts` retries were used up without success.
+    Exhausted(anyhow::Error),
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::TooLarge => write!(f, "response body exceeded max_body_bytes"),
+            FetchError::TooManyRedirects => write!(f, "exceeded max_redirects"),
+            FetchError::Timeout => write!(f, "request timed out"),
+            FetchError::Exhausted(e) => write!(f, "retries exhausted: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Classify a [`send_request`] failure: a transport timeout becomes
+/// [`FetchError::Timeout`] instead of being indistinguishable inside
+/// [`FetchError::Exhausted`].
+fn classify_transport_error(error: anyhow::Error) -> FetchError {
+    match error.downcast_ref::<crate::SendError>() {
+        Some(send_error) if matches!(send_error.kind, crate::SendErrorKind::Timeout) => FetchError::Timeout,
+        _ => FetchError::Exhausted(error),
+    }
+}
+
+/// Guardrails for [`fetch_with`] against a hostile or flaky remote.
+#[derive(Clone, Debug)]
+pub struct FetchOptions {
+    method: http::Method,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+    timeout: Duration,
+    max_attempts: u32,
+    base_delay: Duration,
+    jitter: Duration,
+    max_body_bytes: usize,
+    max_redirects: u32,
+    check_expected_url: bool,
+}
+
+impl FetchOptions {
+    /// A `method` request with conservative defaults: 3 attempts, 500ms base
+    /// backoff, 10MB body cap, 5 redirects, 30s per-attempt timeout.
+    pub fn new(method: http::Method) -> Self {
+        Self {
+            method,
+            headers: HashMap::new(),
+            body: Vec::new(),
+            timeout: Duration::from_secs(30),
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            jitter: Duration::from_millis(100),
+            max_body_bytes: 10 * 1024 * 1024,
+            max_redirects: 5,
+            check_expected_url: false,
+        }
+    }
+
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn backoff(mut self, base_delay: Duration, jitter: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.jitter = jitter;
+        self
+    }
+
+    /// Reject a response whose body exceeds `max_body_bytes` with
+    /// [`FetchError::TooLarge`]. Note this is checked once the runtime has
+    /// already buffered the full body in memory, not enforced as a
+    /// streaming cap -- a remote that may return truly large, untrusted-size
+    /// payloads should use `crate::stream` instead.
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    pub fn max_redirects(mut self, max_redirects: u32) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// After following redirects, if the final response's self-reported
+    /// canonical URL doesn't match what was requested, re-fetch that
+    /// canonical URL once before giving up. Useful when a server normalizes
+    /// or re-cases an identifier in a way a naive redirect-follow misses.
+    pub fn check_expected_url(mut self, check: bool) -> Self {
+        self.check_expected_url = check;
+        self
+    }
+}
+
+/// Fetch `url` according to `options`: retries transport/5xx errors up to
+/// `max_attempts`, waiting `base_delay` doubled per retry plus the fixed
+/// `jitter` duration (the jitter is not randomized), aborts with
+/// [`FetchError::TooLarge`] if the response exceeds `max_body_bytes`, stops
+/// following redirects past `max_redirects` with
+/// [`FetchError::TooManyRedirects`], and times each attempt out at
+/// `timeout`.
+pub fn fetch_with(url: &str, options: FetchOptions) -> Result<http::Response<Vec<u8>>, FetchError> {
+    let mut last_err = None;
+    for attempt in 0..options.max_attempts {
+        if attempt > 0 {
+            let backoff = options.base_delay * 2u32.pow(attempt.saturating_sub(1)) + options.jitter;
+            let _ = crate::timer::set_timer(backoff, None);
+            let _ = crate::await_message();
+        }
+        match fetch_once(url, &options) {
+            Ok(response) => return Ok(response),
+            Err(FetchError::TooLarge) => return Err(FetchError::TooLarge),
+            Err(FetchError::TooManyRedirects) => return Err(FetchError::TooManyRedirects),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.expect("max_attempts is always at least 1, so fetch_once ran at least once"))
+}
+
+fn fetch_once(url: &str, options: &FetchOptions) -> Result<http::Response<Vec<u8>>, FetchError> {
+    let mut current_url = url.to_string();
+    for redirect in 0..=options.max_redirects {
+        let response = send_request(
+            options.method.clone(),
+            &current_url,
+            options.headers.clone(),
+            options.body.clone(),
+            options.timeout,
+        )
+        .map_err(classify_transport_error)?;
+
+        // `send_request` only returns once the runtime has handed back the
+        // full body, so this rejects an oversized response after it's
+        // already been buffered in memory rather than preventing the
+        // buffering itself -- there's no lower-level hook here to cap the
+        // transfer mid-flight. A remote that may return truly large,
+        // untrusted-size payloads should use `crate::stream` instead, which
+        // never holds more than one chunk at a time.
+        if response.body().len() > options.max_body_bytes {
+            return Err(FetchError::TooLarge);
+        }
+
+        if response.status().is_server_error() {
+            return Err(FetchError::Exhausted(anyhow::anyhow!(
+                "server error: {}",
+                response.status()
+            )));
+        }
+
+        if response.status().is_redirection() {
+            if redirect == options.max_redirects {
+                return Err(FetchError::TooManyRedirects);
+            }
+            if let Some(location) = response.headers().get(http::header::LOCATION) {
+                current_url = location.to_str().map_err(|e| FetchError::Exhausted(e.into()))?.to_string();
+                continue;
+            }
+        }
+
+        if options.check_expected_url {
+            if let Some(canonical) = response
+                .headers()
+                .get("x-canonical-url")
+                .and_then(|v| v.to_str().ok())
+            {
+                if canonical != url && canonical != current_url {
+                    let refetched = send_request(
+                        options.method.clone(),
+                        canonical,
+                        options.headers.clone(),
+                        options.body.clone(),
+                        options.timeout,
+                    )
+                    .map_err(classify_transport_error)?;
+                    if refetched.body().len() > options.max_body_bytes {
+                        return Err(FetchError::TooLarge);
+                    }
+                    if refetched.status().is_server_error() {
+                        return Err(FetchError::Exhausted(anyhow::anyhow!(
+                            "server error: {}",
+                            refetched.status()
+                        )));
+                    }
+                    return Ok(refetched);
+                }
+            }
+        }
+
+        return Ok(response);
+    }
+    Err(FetchError::TooManyRedirects)
+}
@@ -0,0 +1,98 @@
+//! Interact with the key_value module.
+//!
+//! A [`Kv`] handle is a namespaced key-value store backed by the kv runtime
+//! module. Keys and values are both raw bytes; callers that want typed
+//! values serialize them themselves (see [`crate::cache`] for a layer that
+//! does this plus expiry on top).
+
+use crate::{Address, ProcessId, Request};
+use serde::{Deserialize, Serialize};
+
+/// A handle to one named key-value database owned by this process.
+#[derive(Clone, Debug)]
+pub struct Kv {
+    pub db: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum KvAction {
+    Get { key: Vec<u8> },
+    Set { key: Vec<u8> },
+    Delete { key: Vec<u8> },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct KvRequest {
+    db: String,
+    action: KvAction,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum KvResponse {
+    Ok,
+    Err(String),
+    Value,
+}
+
+fn kv_address() -> Address {
+    Address::new("our", ProcessId::new(Some("kv"), "distro", "sys"))
+}
+
+/// Open (creating if necessary) the named key-value database.
+pub fn open(db: &str) -> anyhow::Result<Kv> {
+    Ok(Kv { db: db.to_string() })
+}
+
+impl Kv {
+    /// Fetch the raw bytes stored at `key`, via this message's blob.
+    /// Returns `None` if the key is absent.
+    pub fn get(&self, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        Request::new()
+            .target(kv_address())
+            .body(serde_json::to_vec(&KvRequest {
+                db: self.db.clone(),
+                action: KvAction::Get {
+                    key: key.to_vec(),
+                },
+            })?)
+            .send()?;
+        let message = crate::await_message()?;
+        match serde_json::from_slice::<KvResponse>(message.body())? {
+            KvResponse::Value => Ok(crate::get_blob().map(|b| b.bytes)),
+            KvResponse::Ok => Ok(None),
+            KvResponse::Err(e) => Err(anyhow::anyhow!(e)),
+        }
+    }
+
+    /// Store `value` at `key`, overwriting any existing value.
+    pub fn set(&self, key: &[u8], value: Vec<u8>) -> anyhow::Result<()> {
+        Request::new()
+            .target(kv_address())
+            .body(serde_json::to_vec(&KvRequest {
+                db: self.db.clone(),
+                action: KvAction::Set {
+                    key: key.to_vec(),
+                },
+            })?)
+            .blob(crate::LazyLoadBlob {
+                mime: None,
+                bytes: value,
+            })
+            .send()?;
+        Ok(())
+    }
+
+    /// Remove `key`, if present.
+    pub fn delete(&self, key: &[u8]) -> anyhow::Result<()> {
+        Request::new()
+            .target(kv_address())
+            .body(serde_json::to_vec(&KvRequest {
+                db: self.db.clone(),
+                action: KvAction::Delete {
+                    key: key.to_vec(),
+                },
+            })?)
+            .send()?;
+        Ok(())
+    }
+}
@@ -14,6 +14,7 @@
 //! For blobs, we recommend bincode to serialize and deserialize to bytes.
 //!
 pub use crate::kinode::process::standard::*;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -22,6 +23,10 @@ wit_bindgen::generate!({
     world: "lib",
 });
 
+/// Anti-entropy state synchronization for replicated processes.
+pub mod anti_entropy;
+/// A TTL cache layered over the key_value module.
+pub mod cache;
 /// Interact with the eth provider module.
 pub mod eth;
 /// Interact with the HTTP server and client modules.
@@ -35,10 +40,14 @@ pub mod kernel_types;
 pub mod kv;
 /// Interact with the sqlite module
 pub mod sqlite;
+/// Chunked/streaming transfer of large blobs over Request/Response.
+pub mod stream;
 /// Interact with the timer runtime module.
 pub mod timer;
 /// Interact with the virtual filesystem
 pub mod vfs;
+/// Ergonomic worker spawning on top of `spawn`/`await_message`.
+pub mod worker;
 
 // Types
 
@@ -61,6 +70,8 @@ mod capability;
 pub use capability::Capability;
 mod lazy_load_blob;
 pub use lazy_load_blob::LazyLoadBlob;
+mod serde_format;
+pub use serde_format::SerdeFormat;
 
 /// Implement the wit-bindgen specific code that the kernel uses to hook into
 /// a process. Write an `init(our: Address)` function and call it with this.
@@ -149,6 +160,17 @@ where
     })
 }
 
+/// Create a blob by serializing `blob` with a given [`SerdeFormat`] instead
+/// of a hand-rolled serializer closure, stamping the resulting
+/// [`LazyLoadBlob`]'s `mime` field so a receiving process can tell which
+/// codec was used.
+pub fn make_blob_as<T: Serialize>(format: SerdeFormat, blob: &T) -> anyhow::Result<LazyLoadBlob> {
+    Ok(LazyLoadBlob {
+        mime: Some(format.mime().to_string()),
+        bytes: format.encode(blob)?,
+    })
+}
+
 /// Fetch the blob of the most recent message we've received. Returns `None`
 /// if that message had no blob. If it does have one, attempt to deserialize
 /// it from bytes with the provided function.
@@ -173,6 +195,13 @@ where
     }
 }
 
+/// Like [`get_typed_blob`], but decodes with a specific [`SerdeFormat`]
+/// instead of a hand-rolled deserializer closure. Returns `None` if there is
+/// no blob, or if decoding with `format` fails.
+pub fn get_typed_blob_with<T: DeserializeOwned>(format: SerdeFormat) -> Option<T> {
+    format.decode(&crate::get_blob()?.bytes).ok()
+}
+
 /// Fetch the persisted state blob associated with this process. This blob is saved
 /// using the [`set_state`] function. Returns `None` if this process has no saved state.
 /// If it does, attempt to deserialize it from bytes with the provided function.
@@ -197,6 +226,21 @@ where
     }
 }
 
+/// Like [`get_typed_state`], but decodes with a specific [`SerdeFormat`].
+/// Returns `None` if there is no saved state, or if decoding with `format`
+/// fails.
+pub fn get_typed_state_with<T: DeserializeOwned>(format: SerdeFormat) -> Option<T> {
+    format.decode(&crate::get_state()?).ok()
+}
+
+/// Persist `state` by serializing it with a given [`SerdeFormat`] instead of
+/// a hand-rolled serializer closure. Saved state can be read back with
+/// [`get_typed_state_with`] using the same format.
+pub fn set_state_as<T: Serialize>(format: SerdeFormat, state: &T) -> anyhow::Result<()> {
+    crate::set_state(&format.encode(state)?);
+    Ok(())
+}
+
 /// See if we have the capability to message a certain process.
 /// Note if you have not saved the capability, you will not be able to message the other process.
 pub fn can_message(address: &Address) -> bool {
@@ -0,0 +1,85 @@
+//! Serialization format negotiation for blobs and state.
+//!
+//! This library's convention is JSON for IPC bodies and a caller-chosen
+//! format (commonly bincode) for blobs and persisted state, but until now
+//! picking that format meant hand-rolling a serializer/deserializer closure
+//! for every call to [`crate::make_blob`], [`crate::get_typed_blob`], and
+//! [`crate::get_typed_state`]. [`SerdeFormat`] names the supported codecs so
+//! a process can pick one without writing closures, and so the format can be
+//! read back off the blob's `mime` field by whoever receives it.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A wire format for encoding a blob or persisted state.
+///
+/// `Json` is always available. The other variants are gated behind cargo
+/// features so that processes which don't need them aren't forced to pull
+/// in the corresponding crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SerdeFormat {
+    /// `application/json`, via `serde_json`.
+    Json,
+    /// `application/bincode`, via the `bincode` crate. Requires the
+    /// `serialize_bincode` feature.
+    #[cfg(feature = "serialize_bincode")]
+    Bincode,
+    /// `application/msgpack`, via `rmp-serde`. Requires the
+    /// `serialize_rmp` feature.
+    #[cfg(feature = "serialize_rmp")]
+    MessagePack,
+    /// `application/postcard`, via the `postcard` crate. Requires the
+    /// `serialize_postcard` feature.
+    #[cfg(feature = "serialize_postcard")]
+    Postcard,
+}
+
+impl SerdeFormat {
+    /// The MIME string stamped onto a [`crate::LazyLoadBlob`] encoded with
+    /// this format, so a cooperating process can tell which codec to use
+    /// without being told out of band.
+    pub fn mime(&self) -> &'static str {
+        match self {
+            SerdeFormat::Json => "application/json",
+            #[cfg(feature = "serialize_bincode")]
+            SerdeFormat::Bincode => "application/bincode",
+            #[cfg(feature = "serialize_rmp")]
+            SerdeFormat::MessagePack => "application/msgpack",
+            #[cfg(feature = "serialize_postcard")]
+            SerdeFormat::Postcard => "application/postcard",
+        }
+    }
+
+    /// Encode `value` into bytes using this format.
+    pub fn encode<T: Serialize>(&self, value: &T) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            SerdeFormat::Json => serde_json::to_vec(value)?,
+            #[cfg(feature = "serialize_bincode")]
+            SerdeFormat::Bincode => bincode::serialize(value)?,
+            #[cfg(feature = "serialize_rmp")]
+            SerdeFormat::MessagePack => rmp_serde::to_vec(value)?,
+            #[cfg(feature = "serialize_postcard")]
+            SerdeFormat::Postcard => postcard::to_allocvec(value)?,
+        })
+    }
+
+    /// Decode `bytes` into `T` using this format.
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> anyhow::Result<T> {
+        Ok(match self {
+            SerdeFormat::Json => serde_json::from_slice(bytes)?,
+            #[cfg(feature = "serialize_bincode")]
+            SerdeFormat::Bincode => bincode::deserialize(bytes)?,
+            #[cfg(feature = "serialize_rmp")]
+            SerdeFormat::MessagePack => rmp_serde::from_slice(bytes)?,
+            #[cfg(feature = "serialize_postcard")]
+            SerdeFormat::Postcard => postcard::from_bytes(bytes)?,
+        })
+    }
+}
+
+impl Default for SerdeFormat {
+    /// Defaults to JSON, matching this library's existing IPC convention.
+    fn default() -> Self {
+        SerdeFormat::Json
+    }
+}
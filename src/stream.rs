@@ -0,0 +1,303 @@
+//! Chunked transfer of large blobs over [`Request`]/[`Response`].
+//!
+//! [`LazyLoadBlob`] forces an entire payload into a single message, which is
+//! wasteful for multi-megabyte files: the whole buffer has to be resident at
+//! once on both ends. This module layers a simple chunked transfer protocol
+//! on top of ordinary messaging so a sender can push a large payload as a
+//! sequence of [`StreamChunk`]s and a receiver can reassemble (or even
+//! iterate) it without ever buffering more than it has to.
+//!
+//! The receiver acknowledges every [`ACK_INTERVAL`] chunks; the sender
+//! blocks on that ack via [`crate::await_message`] before sending more,
+//! giving cheap backpressure against a slow or unready receiver.
+
+use crate::{Address, Request, SendError};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Number of chunks the sender may have in flight before it must wait for a
+/// [`StreamAck`].
+pub const ACK_INTERVAL: u64 = 16;
+
+/// A single chunk of a streamed transfer. Chunks for a given `stream_id`
+/// must be reassembled in `seq` order; `fin` marks the last chunk and
+/// carries the total length of the complete payload so the receiver can
+/// verify nothing was dropped.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamChunk {
+    pub stream_id: u64,
+    pub seq: u64,
+    pub bytes: Vec<u8>,
+    pub fin: Option<u64>,
+}
+
+/// Sent by the receiver every [`ACK_INTERVAL`] chunks to let the sender keep
+/// going.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamAck {
+    pub stream_id: u64,
+    /// The highest contiguous `seq` the receiver has accepted so far.
+    pub through_seq: u64,
+}
+
+/// Reassembles out-of-order [`StreamChunk`]s for one stream into a
+/// contiguous byte sequence.
+///
+/// Internally this is a rope of `Vec<u8>` segments (a `VecDeque` tracking
+/// total length) rather than one growing buffer, so appending a chunk never
+/// requires reallocating or shifting previously-received bytes.
+pub struct StreamReassembler {
+    stream_id: u64,
+    next_seq: u64,
+    total_len: usize,
+    fin_len: Option<u64>,
+    ready: VecDeque<Vec<u8>>,
+    pending: VecDeque<StreamChunk>,
+}
+
+impl StreamReassembler {
+    pub fn new(stream_id: u64) -> Self {
+        Self {
+            stream_id,
+            next_seq: 0,
+            total_len: 0,
+            fin_len: None,
+            ready: VecDeque::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Accept a chunk, buffering it if it arrived out of order. Returns
+    /// [`Self::is_complete`] as it stands after this call (so `true` on
+    /// every call once the stream is complete).
+    pub fn accept(&mut self, chunk: StreamChunk) -> bool {
+        if chunk.seq < self.next_seq || self.pending.iter().any(|c| c.seq == chunk.seq) {
+            // Already-seen retransmission (accepted or still buffered); ignore.
+            return self.is_complete();
+        }
+        self.pending.push_back(chunk);
+        // Keep pending sorted by seq so we can pop contiguous runs off the
+        // front cheaply; out-of-order arrivals are rare enough that an
+        // insertion sort over a short buffer is fine.
+        let len = self.pending.len();
+        for i in (1..len).rev() {
+            if self.pending[i - 1].seq > self.pending[i].seq {
+                self.pending.swap(i - 1, i);
+            }
+        }
+        while let Some(front) = self.pending.front() {
+            if front.seq != self.next_seq {
+                break;
+            }
+            let chunk = self.pending.pop_front().unwrap();
+            self.total_len += chunk.bytes.len();
+            if let Some(fin_len) = chunk.fin {
+                self.fin_len = Some(fin_len);
+            }
+            self.ready.push_back(chunk.bytes);
+            self.next_seq += 1;
+        }
+        self.is_complete()
+    }
+
+    /// `true` once the `fin` chunk has arrived and every byte up to it has
+    /// been reassembled.
+    pub fn is_complete(&self) -> bool {
+        matches!(self.fin_len, Some(len) if len as usize == self.total_len)
+    }
+
+    /// Pop the next contiguous segment of reassembled bytes, if any. Useful
+    /// for incremental processing without waiting for [`Self::is_complete`].
+    pub fn pop_ready(&mut self) -> Option<Vec<u8>> {
+        self.ready.pop_front()
+    }
+
+    /// Consume the reassembler and concatenate the reassembled segments not
+    /// yet taken with [`Self::pop_ready`] into one buffer. Out-of-order
+    /// chunks still waiting for a gap to fill are not included.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.total_len);
+        while let Some(segment) = self.ready.pop_front() {
+            out.extend_from_slice(&segment);
+        }
+        out
+    }
+
+    pub fn stream_id(&self) -> u64 {
+        self.stream_id
+    }
+
+    /// The seq one past the highest contiguous seq accepted so far -- i.e.
+    /// the seq we're still waiting on.
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+}
+
+/// Send `bytes` to `target` as a sequence of [`StreamChunk`] requests,
+/// waiting for a [`StreamAck`] every [`ACK_INTERVAL`] chunks before sending
+/// more.
+pub fn send_stream(
+    target: &Address,
+    stream_id: u64,
+    bytes: &[u8],
+    chunk_size: usize,
+) -> anyhow::Result<()> {
+    let total = bytes.len() as u64;
+    let chunks = bytes.chunks(chunk_size.max(1));
+    let num_chunks = chunks.len() as u64;
+    for (seq, chunk) in chunks.enumerate() {
+        let seq = seq as u64;
+        let fin = if seq + 1 == num_chunks { Some(total) } else { None };
+        let chunk = StreamChunk {
+            stream_id,
+            seq,
+            bytes: chunk.to_vec(),
+            fin,
+        };
+        Request::new()
+            .target(target.clone())
+            .body(serde_json::to_vec(&chunk)?)
+            .send()?;
+        if fin.is_none() && (seq + 1) % ACK_INTERVAL == 0 {
+            await_ack(stream_id, seq)?;
+        }
+    }
+    Ok(())
+}
+
+fn await_ack(stream_id: u64, through_seq: u64) -> anyhow::Result<()> {
+    loop {
+        let message = crate::await_message()?;
+        if let Ok(ack) = serde_json::from_slice::<StreamAck>(message.body()) {
+            if ack.stream_id == stream_id && ack.through_seq >= through_seq {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Receive and reassemble a single stream, yielding reassembled segments as
+/// they become contiguous rather than buffering the whole payload before
+/// returning anything.
+///
+/// Acks are sent back to `sender` every [`ACK_INTERVAL`] chunks. Apart from
+/// right after yielding an `Err` from [`crate::await_message`], the iterator
+/// ends (returns `None`) only once the `fin` chunk's declared length matches
+/// what was actually reassembled, so a truncated sender shows up as the
+/// iterator simply never completing rather than silently returning a short
+/// buffer.
+pub fn recv_stream(
+    sender: Address,
+    stream_id: u64,
+) -> impl Iterator<Item = Result<Vec<u8>, SendError>> {
+    let mut reassembler = StreamReassembler::new(stream_id);
+    let mut pending_emit: VecDeque<Vec<u8>> = VecDeque::new();
+    let mut done = false;
+    std::iter::from_fn(move || loop {
+        if let Some(segment) = pending_emit.pop_front() {
+            return Some(Ok(segment));
+        }
+        if done {
+            return None;
+        }
+        let message = match crate::await_message() {
+            Ok(m) => m,
+            Err(e) => {
+                done = true;
+                return Some(Err(e));
+            }
+        };
+        let Ok(chunk) = serde_json::from_slice::<StreamChunk>(message.body()) else {
+            continue;
+        };
+        if chunk.stream_id != stream_id {
+            continue;
+        }
+        let prev_next_seq = reassembler.next_seq();
+        let complete = reassembler.accept(chunk);
+        let next_seq = reassembler.next_seq();
+        // Gate on the reassembler's contiguous progress, not the raw
+        // arriving seq: out-of-order delivery means the chunk that happens
+        // to be a multiple of ACK_INTERVAL can arrive before the gap
+        // before it is filled, so triggering off its seq directly can miss
+        // the boundary entirely and stall the sender's `await_ack`.
+        if next_seq / ACK_INTERVAL != prev_next_seq / ACK_INTERVAL || complete {
+            let through_seq = next_seq.saturating_sub(1);
+            let _ = Request::new()
+                .target(sender.clone())
+                .body(
+                    serde_json::to_vec(&StreamAck {
+                        stream_id,
+                        through_seq,
+                    })
+                    .unwrap_or_default(),
+                )
+                .send();
+        }
+        while let Some(segment) = reassembler.pop_ready() {
+            pending_emit.push_back(segment);
+        }
+        if complete {
+            done = true;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(stream_id: u64, seq: u64, bytes: &[u8], fin: Option<u64>) -> StreamChunk {
+        StreamChunk {
+            stream_id,
+            seq,
+            bytes: bytes.to_vec(),
+            fin,
+        }
+    }
+
+    #[test]
+    fn reassembles_in_order_chunks() {
+        let mut r = StreamReassembler::new(1);
+        assert!(!r.accept(chunk(1, 0, b"ab", None)));
+        assert!(!r.is_complete());
+        assert!(r.accept(chunk(1, 1, b"cd", Some(4))));
+        assert!(r.is_complete());
+        assert_eq!(r.into_bytes(), b"abcd");
+    }
+
+    #[test]
+    fn buffers_out_of_order_chunks_until_contiguous() {
+        let mut r = StreamReassembler::new(1);
+        // seq 1 arrives before seq 0: nothing should be ready yet.
+        assert!(!r.accept(chunk(1, 1, b"cd", Some(4))));
+        assert_eq!(r.next_seq(), 0);
+        assert!(r.pop_ready().is_none());
+        // seq 0 arrives: both 0 and the buffered 1 become contiguous at once.
+        assert!(r.accept(chunk(1, 0, b"ab", None)));
+        assert!(r.is_complete());
+        assert_eq!(r.pop_ready(), Some(b"ab".to_vec()));
+        assert_eq!(r.pop_ready(), Some(b"cd".to_vec()));
+        assert_eq!(r.pop_ready(), None);
+    }
+
+    #[test]
+    fn ignores_duplicate_retransmissions() {
+        let mut r = StreamReassembler::new(1);
+        r.accept(chunk(1, 0, b"ab", None));
+        // A retransmit of an already-accepted chunk shouldn't re-append bytes.
+        r.accept(chunk(1, 0, b"ab", None));
+        r.accept(chunk(1, 1, b"cd", Some(4)));
+        assert_eq!(r.into_bytes(), b"abcd");
+    }
+
+    #[test]
+    fn is_not_complete_until_fin_length_matches_reassembled_length() {
+        let mut r = StreamReassembler::new(1);
+        r.accept(chunk(1, 0, b"ab", None));
+        // fin declares more bytes than have arrived so far.
+        assert!(!r.accept(chunk(1, 1, b"cd", Some(10))));
+        assert!(!r.is_complete());
+    }
+}
@@ -0,0 +1,26 @@
+//! Interact with the timer runtime module.
+//!
+//! The timer runtime module sends this process a message after a delay, so
+//! periodic or deferred work can be driven from the normal
+//! [`crate::await_message`] loop instead of needing a dedicated scheduler.
+
+use crate::{Address, ProcessId, Request};
+use std::time::Duration;
+
+fn timer_address() -> Address {
+    Address::new("our", ProcessId::new(Some("timer"), "distro", "sys"))
+}
+
+/// Ask the timer runtime module to send us a message after `duration`. The
+/// message body is empty; use `context` (delivered back unchanged on
+/// [`crate::Message`]) to tell multiple in-flight timers apart.
+pub fn set_timer(duration: Duration, context: Option<Vec<u8>>) -> anyhow::Result<()> {
+    let mut request = Request::new()
+        .target(timer_address())
+        .body(duration.as_millis().to_string().into_bytes());
+    if let Some(context) = context {
+        request = request.context(context);
+    }
+    request.send()?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//! Interact with the virtual filesystem.
+//!
+//! The vfs runtime module speaks a small request/response protocol over the
+//! message body: a [`VfsRequest`] naming a `path` and a [`VfsAction`], and a
+//! matching [`VfsResponse`]. This module wraps that protocol, plus (below)
+//! a watch subsystem for reacting to filesystem changes instead of polling.
+
+use crate::{Address, ProcessId, Request};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A request to the vfs runtime module.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VfsRequest {
+    pub path: String,
+    pub action: VfsAction,
+}
+
+/// An operation on a path, understood by the vfs runtime module.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum VfsAction {
+    CreateFile,
+    Read,
+    Write,
+    Remove,
+    /// Subscribe to changes under `path`. See [`watch_path`].
+    Watch { recursive: bool },
+    /// Cancel a subscription previously made with `Watch`.
+    Unwatch { watch_id: u64 },
+}
+
+/// The vfs runtime module's reply to a [`VfsRequest`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum VfsResponse {
+    Ok,
+    Err(String),
+    Read,
+    /// Acknowledges a `Watch` request and assigns it an id to unwatch later.
+    Watching { watch_id: u64 },
+}
+
+fn vfs_address() -> Address {
+    Address::new("our", ProcessId::new(Some("vfs"), "distro", "sys"))
+}
+
+/// Ask the vfs module to read the full contents of the file at `path`. The
+/// bytes arrive as the blob of its reply; this function only sends the
+/// request and does not wait for that reply.
+pub fn read_file(path: &str) -> anyhow::Result<()> {
+    Request::new()
+        .target(vfs_address())
+        .body(serde_json::to_vec(&VfsRequest {
+            path: path.to_string(),
+            action: VfsAction::Read,
+        })?)
+        .send()?;
+    Ok(())
+}
+
+/// Overwrite the file at `path` with `bytes`.
+pub fn write_file(path: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+    Request::new()
+        .target(vfs_address())
+        .body(serde_json::to_vec(&VfsRequest {
+            path: path.to_string(),
+            action: VfsAction::Write,
+        })?)
+        .blob(crate::LazyLoadBlob { mime: None, bytes })
+        .send()?;
+    Ok(())
+}
+
+// --- Filesystem watching ---
+
+/// Identifies one active [`watch_path`] subscription, returned so it can
+/// later be passed to [`unwatch`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WatchId(u64);
+
+/// A filesystem change delivered to a process that called [`watch_path`].
+/// These arrive interleaved with other messages in the normal
+/// [`crate::await_message`] loop; use [`as_fs_event`] to pick them out.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FsEvent {
+    pub watch_id: WatchId,
+    pub path: String,
+    pub kind: FsEventKind,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FsEventKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed { to: String },
+}
+
+/// How long a burst of events (of any kind) for the same path is coalesced into
+/// a single delivered [`FsEvent`], so a process writing a large file in many
+/// small chunks doesn't flood its subscriber with `Modified` events.
+pub const DEBOUNCE_MILLIS: u64 = 200;
+
+/// Subscribe to changes under `path` (a single file, or a directory if
+/// `recursive` is set). Returns the [`WatchId`] to pass to [`unwatch`] when
+/// done; matching [`FsEvent`]s will start arriving via
+/// [`crate::await_message`], demultiplexed from other traffic with
+/// [`as_fs_event`].
+pub fn watch_path(path: &str, recursive: bool) -> anyhow::Result<WatchId> {
+    let message = Request::new()
+        .target(vfs_address())
+        .body(serde_json::to_vec(&VfsRequest {
+            path: path.to_string(),
+            action: VfsAction::Watch { recursive },
+        })?)
+        .send_and_await_response(5)??;
+    match serde_json::from_slice::<VfsResponse>(message.body())? {
+        VfsResponse::Watching { watch_id } => Ok(WatchId(watch_id)),
+        other => Err(anyhow::anyhow!("unexpected vfs response to Watch: {other:?}")),
+    }
+}
+
+/// Cancel a subscription previously returned by [`watch_path`]; no more
+/// [`FsEvent`]s for it will be delivered afterwards.
+pub fn unwatch(watch_id: WatchId) -> anyhow::Result<()> {
+    Request::new()
+        .target(vfs_address())
+        .body(serde_json::to_vec(&VfsRequest {
+            path: String::new(),
+            action: VfsAction::Unwatch {
+                watch_id: watch_id.0,
+            },
+        })?)
+        .send()?;
+    Ok(())
+}
+
+/// Try to interpret an incoming message's body as an [`FsEvent`], to
+/// demultiplex vfs watch notifications from the rest of a process's message
+/// loop:
+///
+/// ```ignore
+/// let message = await_message()?;
+/// if let Some(event) = vfs::as_fs_event(&message) {
+///     // handle the change
+/// } else {
+///     // handle everything else as before
+/// }
+/// ```
+pub fn as_fs_event(message: &crate::Message) -> Option<FsEvent> {
+    serde_json::from_slice(message.body()).ok()
+}
+
+/// Records when each (watch, path) pair last produced an event and applies
+/// the debounce window described by [`DEBOUNCE_MILLIS`], so callers that
+/// want coalesced events rather than raw ones can pass the events they get
+/// from [`as_fs_event`] through [`Debouncer::observe`]. It does not track
+/// which subscriptions are active: [`watch_path`] and [`unwatch`] do not
+/// register with it.
+pub struct Debouncer {
+    last_seen_millis: HashMap<(WatchId, String), u64>,
+}
+
+impl Debouncer {
+    pub fn new() -> Self {
+        Self {
+            last_seen_millis: HashMap::new(),
+        }
+    }
+
+    /// Feed an [`FsEvent`] in along with the current time (in milliseconds,
+    /// from whatever clock the caller tracks); returns `Some(event)` unless
+    /// an event for the same path was already observed within
+    /// [`DEBOUNCE_MILLIS`], in which case it's swallowed.
+    pub fn observe(&mut self, event: FsEvent, now_millis: u64) -> Option<FsEvent> {
+        let key = (event.watch_id, event.path.clone());
+        if let Some(&last) = self.last_seen_millis.get(&key) {
+            if now_millis.saturating_sub(last) < DEBOUNCE_MILLIS {
+                self.last_seen_millis.insert(key, now_millis);
+                return None;
+            }
+        }
+        self.last_seen_millis.insert(key, now_millis);
+        Some(event)
+    }
+}
+
+impl Default for Debouncer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,138 @@
+//! Ergonomic worker spawning on top of [`crate::spawn`]/[`crate::await_message`].
+//!
+//! Building something like a file-transfer flow on raw `spawn` +
+//! `await_message` means hand-matching raw bodies against a request/response
+//! protocol and re-wiring [`OnExit`] every time. [`WorkerApi`] names that
+//! protocol as a pair of serializable enums, and [`start_worker`] does the
+//! spawn/wire-up/initial-request boilerplate once, handing back a
+//! [`WorkerHandle`] the parent can poll with [`WorkerHandle::next_update`]
+//! for the worker's `Response` messages (e.g. progress updates) as they
+//! arrive via the normal message loop.
+
+use crate::{Address, Capability, OnExit, ProcessId, Request, SendError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// Defines the request/response protocol a worker process speaks.
+///
+/// Both associated types are serialized through the message body (as JSON,
+/// matching this library's IPC convention) rather than the blob, so that a
+/// parent can pattern-match on them directly.
+pub trait WorkerApi {
+    /// Requests the parent may send to the worker.
+    type Request: Serialize + DeserializeOwned;
+    /// Responses (including progress updates) the worker sends back.
+    type Response: Serialize + DeserializeOwned;
+}
+
+/// A handle to a spawned worker process, typed to the protocol it speaks.
+pub struct WorkerHandle<W: WorkerApi> {
+    pub address: Address,
+    _protocol: PhantomData<W>,
+}
+
+impl<W: WorkerApi> WorkerHandle<W> {
+    /// Block until the worker sends its next message, and decode it as
+    /// `W::Response`. Messages that fail to decode (e.g. an `OnExit` from
+    /// the kernel) are skipped rather than surfaced as an error, since a
+    /// worker's lifecycle notifications aren't part of its own protocol.
+    pub fn next_update(&self) -> Result<W::Response, SendError> {
+        loop {
+            let message = crate::await_message()?;
+            if message.source() != &self.address {
+                continue;
+            }
+            if let Ok(response) = serde_json::from_slice(message.body()) {
+                return Ok(response);
+            }
+        }
+    }
+
+    /// Send a further request to the already-running worker.
+    pub fn send(&self, request: &W::Request) -> anyhow::Result<()> {
+        Request::new()
+            .target(self.address.clone())
+            .body(serde_json::to_vec(request)?)
+            .send()?;
+        Ok(())
+    }
+}
+
+/// Spawn `wasm_path` as a child process speaking protocol `W`, send it
+/// `init_request` as its first message, and return a handle the parent can
+/// poll for responses (progress updates, results, etc.) via
+/// [`WorkerHandle::next_update`].
+///
+/// The child is spawned non-public with only the capabilities the caller
+/// passes in `request_capabilities` and `grant_capabilities`. What happens
+/// when it exits is whatever the caller passes as `on_exit`; nothing else is
+/// wired up for its exit.
+pub fn start_worker<W: WorkerApi>(
+    name: Option<&str>,
+    wasm_path: &str,
+    on_exit: OnExit,
+    request_capabilities: Vec<Capability>,
+    grant_capabilities: Vec<ProcessId>,
+    init_request: &W::Request,
+) -> anyhow::Result<WorkerHandle<W>> {
+    let child = crate::spawn(
+        name,
+        wasm_path,
+        on_exit,
+        request_capabilities,
+        grant_capabilities,
+        false,
+    )?;
+    let address = Address::new("our", child);
+    Request::new()
+        .target(address.clone())
+        .body(serde_json::to_vec(init_request)?)
+        .send()?;
+    Ok(WorkerHandle {
+        address,
+        _protocol: PhantomData,
+    })
+}
+
+/// A ready-made [`WorkerApi`] for a download worker: the parent asks for a
+/// `target` URL to be saved as `file_name`, and receives periodic `Progress`
+/// updates until `Done` or `Failed`.
+pub struct DownloadWorker;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum DownloadRequest {
+    Download { target: String, file_name: String },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum DownloadResponse {
+    Progress { bytes_done: u64, total: u64 },
+    Done { file_name: String },
+    Failed { error: String },
+}
+
+impl WorkerApi for DownloadWorker {
+    type Request = DownloadRequest;
+    type Response = DownloadResponse;
+}
+
+/// Spawn a [`DownloadWorker`] that fetches `target` and saves it as
+/// `file_name`, returning a handle the caller can poll with
+/// [`WorkerHandle::next_update`] for `Progress`/`Done`/`Failed` messages.
+pub fn start_download(
+    wasm_path: &str,
+    target: &str,
+    file_name: &str,
+) -> anyhow::Result<WorkerHandle<DownloadWorker>> {
+    start_worker::<DownloadWorker>(
+        None,
+        wasm_path,
+        OnExit::None,
+        vec![],
+        vec![],
+        &DownloadRequest::Download {
+            target: target.to_string(),
+            file_name: file_name.to_string(),
+        },
+    )
+}